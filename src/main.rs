@@ -1,5 +1,5 @@
 use indicatif::{ParallelProgressIterator, ProgressBar, ProgressStyle};
-use log::{debug, info, warn, LevelFilter};
+use log::{debug, error, info, LevelFilter};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use serde_json::to_string_pretty;
@@ -7,10 +7,13 @@ use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
 use clap::Parser;
-use num_bigint::{BigUint, ToBigUint};
-use num_traits::One;
+use num_bigint::{BigUint, RandBigInt, ToBigUint};
+use num_integer::Integer;
+use num_traits::{One, Zero};
+#[cfg(feature = "gpu")]
+use ocl::ProQue;
+use std::time::{Duration, Instant};
 
 #[derive(Parser, Debug)]
 struct Args {
@@ -18,67 +21,479 @@ struct Args {
     file: PathBuf,
     #[clap(short, long)]
     cache: Option<PathBuf>,
+    /// Offload prime filtering to an OpenCL device (requires the `gpu` feature).
+    #[clap(long)]
+    gpu: bool,
+    /// Re-check a sample of `--gpu` results on the CPU.
+    #[clap(long)]
+    cpu_validate: bool,
+    /// Append a CSV row of per-phase timings to this path (header written on first creation).
+    #[clap(long)]
+    timings: Option<PathBuf>,
 }
 
 #[derive(Serialize, Deserialize)]
 struct PrimeFactors {
-    factors: HashMap<u64, u64>,
+    /// Keyed by the decimal string of each prime rather than `BigUint`
+    /// itself, since `serde_json` requires map keys to serialize as strings.
+    factors: HashMap<String, u64>,
+}
+
+/// Size (in sieve entries) of each segment processed by the segmented sieve.
+/// 32 MiB of `bool` entries keeps each block cache/VRAM-friendly while still
+/// amortizing the per-block overhead.
+const SEGMENT_SIZE: u64 = 32 * 1024 * 1024;
+
+/// Sieve of Eratosthenes over the small range `[2, limit]`, used as the
+/// source of "base" primes for the segmented sieve below.
+fn sieve_base_primes(limit: u64) -> Vec<u64> {
+    if limit < 2 {
+        return Vec::new();
+    }
+    let limit = limit as usize;
+    let mut is_prime = vec![true; limit + 1];
+    is_prime[0] = false;
+    is_prime[1] = false;
+    let mut i = 2usize;
+    while i * i <= limit {
+        if is_prime[i] {
+            let mut j = i * i;
+            while j <= limit {
+                is_prime[j] = false;
+                j += i;
+            }
+        }
+        i += 1;
+    }
+    (2..=limit)
+        .filter(|&i| is_prime[i])
+        .map(|i| i as u64)
+        .collect()
+}
+
+/// Segmented sieve over `[low_bound, n]`, crossing off composites in each
+/// block with the base primes up to `sqrt(n)`. `low_bound` need not be 2;
+/// callers extending a cache pass in one past the previously-cached bound.
+fn sieve_segmented(low_bound: u64, n: u64, bar: &ProgressBar) -> Vec<u64> {
+    if low_bound > n {
+        return Vec::new();
+    }
+
+    // Base primes up to sqrt(n) are used to cross off composites in every
+    // segment; they're small enough to sieve directly and keep in memory.
+    let sqrt_n = (n as f64).sqrt() as u64 + 1;
+    let base_primes = sieve_base_primes(sqrt_n);
+
+    let num_segments = ((n - low_bound) / SEGMENT_SIZE) + 1;
+    let segments: Vec<(u64, u64)> = (0..num_segments)
+        .map(|seg| {
+            let low = low_bound + seg * SEGMENT_SIZE;
+            let high = (low + SEGMENT_SIZE - 1).min(n);
+            (low, high)
+        })
+        .filter(|(low, high)| low <= high)
+        .collect();
+
+    let mut primes: Vec<u64> = segments
+        .into_par_iter()
+        .progress_with(bar.clone())
+        .flat_map(|(low, high)| {
+            let size = (high - low + 1) as usize;
+            let mut is_prime = vec![true; size];
+
+            for &p in &base_primes {
+                if p * p > high {
+                    break;
+                }
+                // Smallest multiple of `p` that is both >= p*p (smaller
+                // multiples were already crossed off by a lesser prime) and
+                // falls inside [low, high].
+                let first_multiple = low.div_ceil(p) * p;
+                let mut j = first_multiple.max(p * p);
+                while j <= high {
+                    is_prime[(j - low) as usize] = false;
+                    j += p;
+                }
+            }
+
+            (low..=high)
+                .filter(|&num| is_prime[(num - low) as usize] && num >= 2)
+                .collect::<Vec<u64>>()
+        })
+        .collect();
+
+    primes.par_sort_unstable();
+    primes
 }
 
 fn generate_primes_up_to(n: u64, cache_file: Option<&PathBuf>) -> Vec<u64> {
-    if let Some(cache_file) = cache_file {
-        if let Ok(cached_primes) = read_primes_from_cache(cache_file) {
-            return cached_primes;
+    if n < 2 {
+        return Vec::new();
+    }
+
+    let cached = cache_file.and_then(|f| read_primes_from_cache(f).ok());
+    if let Some(cache) = &cached {
+        if cache.upper_bound >= n {
+            return cache.primes.iter().copied().take_while(|&p| p <= n).collect();
         }
     }
 
-    let bar = ProgressBar::new(n);
+    let (low_bound, mut primes) = match cached {
+        Some(cache) => (cache.upper_bound + 1, cache.primes),
+        None => (2, Vec::new()),
+    };
+
+    let bar = ProgressBar::new(n - low_bound + 1);
     bar.set_style(
         ProgressStyle::default_bar()
             .template("{msg} {bar:40.cyan/blue} {pos}/{len} {eta}")
             .expect("Failed to set progress bar style")
             .progress_chars("#>-"),
     );
-    bar.set_message("Generating primes");
+    bar.set_message(if low_bound > 2 {
+        "Extending cached primes"
+    } else {
+        "Generating primes"
+    });
 
-    let primes: Vec<u64> = (2..=n)
-        .into_par_iter()
-        .progress_with(bar.clone())
-        .filter(|num| (2..(*num as f64).sqrt() as u64 + 1).all(|i| num % i != 0))
-        .collect();
+    primes.extend(sieve_segmented(low_bound, n, &bar));
 
     bar.finish_with_message("Prime generation completed");
 
     if let Some(cache_file) = cache_file {
-        write_primes_to_cache(cache_file, &primes).expect("Failed to write primes to cache");
+        write_primes_to_cache(
+            cache_file,
+            &PrimeCache {
+                upper_bound: n,
+                primes: primes.clone(),
+            },
+        )
+        .expect("Failed to write primes to cache");
     }
 
     primes
 }
 
-fn read_primes_from_cache(cache_file: &PathBuf) -> Result<Vec<u64>, std::io::Error> {
+/// Wall-clock split for a GPU prime-generation run: time spent dispatching
+/// and waiting on the OpenCL kernel versus time spent compacting survivors
+/// and (optionally) CPU-validating them on the host.
+#[derive(Default, Clone, Copy)]
+struct GpuTimings {
+    dispatch: Duration,
+    host_compaction: Duration,
+}
+
+/// Dispatches to the GPU backend when `use_gpu` is set and this binary was
+/// built with the `gpu` feature, otherwise falls back to the CPU sieve.
+/// Returns the primes plus GPU dispatch/host-compaction timings when the
+/// GPU backend actually ran.
+fn generate_primes(
+    n: u64,
+    cache_file: Option<&PathBuf>,
+    use_gpu: bool,
+    cpu_validate: bool,
+) -> (Vec<u64>, Option<GpuTimings>) {
+    if use_gpu {
+        #[cfg(feature = "gpu")]
+        {
+            let (primes, timings) = generate_primes_up_to_gpu(n, cache_file, cpu_validate);
+            return (primes, Some(timings));
+        }
+        #[cfg(not(feature = "gpu"))]
+        {
+            let _ = cpu_validate;
+            log::warn!(
+                "--gpu was requested but this binary was built without the `gpu` feature; falling back to the CPU sieve"
+            );
+        }
+    }
+    (generate_primes_up_to(n, cache_file), None)
+}
+
+/// Number of candidates dispatched to the GPU per kernel invocation, sized
+/// so the input/output buffers for one chunk comfortably fit in VRAM.
+#[cfg(feature = "gpu")]
+const GPU_CHUNK_SIZE: u64 = 33_554_432;
+
+#[cfg(feature = "gpu")]
+const PRIME_FILTER_KERNEL: &str = r#"
+    __kernel void filter_primes(__global const ulong* candidates, __global uchar* is_prime, uint count) {
+        uint idx = get_global_id(0);
+        if (idx >= count) {
+            return;
+        }
+        ulong n = candidates[idx];
+        if (n < 2) {
+            is_prime[idx] = 0;
+            return;
+        }
+        if (n == 2) {
+            is_prime[idx] = 1;
+            return;
+        }
+        if (n % 2 == 0) {
+            is_prime[idx] = 0;
+            return;
+        }
+        ulong limit = (ulong)sqrt((double)n);
+        for (ulong i = 3; i <= limit; i += 2) {
+            if (n % i == 0) {
+                is_prime[idx] = 0;
+                return;
+            }
+        }
+        is_prime[idx] = 1;
+    }
+"#;
+
+/// GPU-backed mirror of [`generate_primes_up_to`]: offloads the trial
+/// division filter to an OpenCL device, streaming candidates in
+/// `GPU_CHUNK_SIZE`-sized chunks so arbitrarily large ranges fit in VRAM.
+#[cfg(feature = "gpu")]
+fn generate_primes_up_to_gpu(
+    n: u64,
+    cache_file: Option<&PathBuf>,
+    cpu_validate: bool,
+) -> (Vec<u64>, GpuTimings) {
+    let cached = cache_file.and_then(|f| read_primes_from_cache(f).ok());
+    if let Some(cache) = &cached {
+        if cache.upper_bound >= n {
+            let primes = cache.primes.iter().copied().take_while(|&p| p <= n).collect();
+            return (primes, GpuTimings::default());
+        }
+    }
+
+    // Mirrors generate_primes_up_to's incremental cache extension: reuse
+    // whatever primes are already cached and only dispatch the GPU over the
+    // uncovered tail of the range instead of recomputing from 2 every time.
+    let (low_bound, mut primes) = match cached {
+        Some(cache) => (cache.upper_bound + 1, cache.primes),
+        None => (2, Vec::new()),
+    };
+
+    let bar = ProgressBar::new(n - low_bound + 1);
+    bar.set_style(
+        ProgressStyle::default_bar()
+            .template("{msg} {bar:40.cyan/blue} {pos}/{len} {eta}")
+            .expect("Failed to set progress bar style")
+            .progress_chars("#>-"),
+    );
+    bar.set_message("Generating primes (GPU)");
+
+    let pro_que = ProQue::builder()
+        .src(PRIME_FILTER_KERNEL)
+        .dims(GPU_CHUNK_SIZE as usize)
+        .build()
+        .expect("Failed to build OpenCL ProQue");
+
+    let mut timings = GpuTimings::default();
+    let mut low: u64 = low_bound;
+    while low <= n {
+        let high = (low + GPU_CHUNK_SIZE - 1).min(n);
+        let candidates: Vec<u64> = (low..=high).collect();
+        let count = candidates.len();
+
+        let dispatch_start = Instant::now();
+
+        let input_buffer = pro_que
+            .buffer_builder::<u64>()
+            .len(count)
+            .copy_host_slice(&candidates)
+            .build()
+            .expect("Failed to allocate GPU input buffer");
+        let output_buffer = pro_que
+            .buffer_builder::<u8>()
+            .len(count)
+            .build()
+            .expect("Failed to allocate GPU output buffer");
+
+        let kernel = pro_que
+            .kernel_builder("filter_primes")
+            .arg(&input_buffer)
+            .arg(&output_buffer)
+            .arg(count as u32)
+            .global_work_size(count)
+            .build()
+            .expect("Failed to build OpenCL kernel");
+
+        unsafe {
+            kernel.enq().expect("Failed to enqueue OpenCL kernel");
+        }
+
+        let mut flags = vec![0u8; count];
+        output_buffer
+            .read(&mut flags)
+            .enq()
+            .expect("Failed to read GPU results");
+
+        timings.dispatch += dispatch_start.elapsed();
+
+        let compaction_start = Instant::now();
+
+        if cpu_validate {
+            validate_gpu_sample(&candidates, &flags);
+        }
+
+        primes.extend(
+            candidates
+                .iter()
+                .zip(flags.iter())
+                .filter(|&(_, &flag)| flag == 1)
+                .map(|(&num, _)| num),
+        );
+
+        timings.host_compaction += compaction_start.elapsed();
+
+        bar.set_position(high - low_bound + 1);
+        low = high + 1;
+    }
+
+    bar.finish_with_message("GPU prime generation completed");
+
+    if let Some(cache_file) = cache_file {
+        write_primes_to_cache(
+            cache_file,
+            &PrimeCache {
+                upper_bound: n,
+                primes: primes.clone(),
+            },
+        )
+        .expect("Failed to write primes to cache");
+    }
+
+    (primes, timings)
+}
+
+/// Re-checks a strided sample of GPU survivors/rejects against the CPU
+/// trial-division test and warns (without aborting) on any mismatch.
+#[cfg(feature = "gpu")]
+fn validate_gpu_sample(candidates: &[u64], flags: &[u8]) {
+    const SAMPLE_STRIDE: usize = 997;
+    for (&num, &flag) in candidates
+        .iter()
+        .zip(flags.iter())
+        .step_by(SAMPLE_STRIDE)
+    {
+        let cpu_is_prime = num >= 2 && (2..=((num as f64).sqrt() as u64)).all(|d| num % d != 0);
+        if cpu_is_prime != (flag == 1) {
+            log::warn!(
+                "GPU/CPU primality mismatch for {}: gpu={} cpu={}",
+                num,
+                flag == 1,
+                cpu_is_prime
+            );
+        }
+    }
+}
+
+/// Four-byte tag identifying the binary prime cache format, followed by a
+/// one-byte version so the format can evolve without breaking old caches.
+const CACHE_MAGIC: &[u8; 4] = b"PFC1";
+const CACHE_VERSION: u8 = 1;
+
+/// A loaded prime cache: every prime up to (and including) `upper_bound`.
+struct PrimeCache {
+    upper_bound: u64,
+    primes: Vec<u64>,
+}
+
+/// Appends `value` to `buf` as an unsigned LEB128 varint. Prime gaps are
+/// small, so most primes cost a single byte this way.
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value != 0 {
+            buf.push(byte | 0x80);
+        } else {
+            buf.push(byte);
+            break;
+        }
+    }
+}
+
+/// Reads one unsigned LEB128 varint from `buf` starting at `*pos`, advancing
+/// `*pos` past it.
+fn read_varint(buf: &[u8], pos: &mut usize) -> u64 {
+    let mut result = 0u64;
+    let mut shift = 0u32;
+    loop {
+        let byte = buf[*pos];
+        *pos += 1;
+        result |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    result
+}
+
+/// Reads a cache file written by [`write_primes_to_cache`]: a small header
+/// (magic, version, upper bound) followed by primes delta-encoded as
+/// varints, since consecutive prime gaps are small.
+fn read_primes_from_cache(cache_file: &PathBuf) -> Result<PrimeCache, std::io::Error> {
     let mut file = File::open(cache_file)?;
-    let mut buffer = String::new();
-    file.read_to_string(&mut buffer)?;
-    let primes: Vec<u64> = serde_json::from_str(&buffer)?;
-    Ok(primes)
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)?;
+
+    if buf.len() < CACHE_MAGIC.len() + 1 + 8 || &buf[..CACHE_MAGIC.len()] != CACHE_MAGIC {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Unrecognized prime cache format",
+        ));
+    }
+    let mut pos = CACHE_MAGIC.len();
+    let version = buf[pos];
+    pos += 1;
+    if version != CACHE_VERSION {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Unsupported prime cache version {version}"),
+        ));
+    }
+    let upper_bound = u64::from_le_bytes(buf[pos..pos + 8].try_into().unwrap());
+    pos += 8;
+
+    let mut primes = Vec::new();
+    let mut prev = 0u64;
+    while pos < buf.len() {
+        prev += read_varint(&buf, &mut pos);
+        primes.push(prev);
+    }
+
+    Ok(PrimeCache {
+        upper_bound,
+        primes,
+    })
 }
 
-fn write_primes_to_cache(cache_file: &PathBuf, primes: &[u64]) -> Result<(), std::io::Error> {
+/// Writes `cache` in the binary format read by [`read_primes_from_cache`].
+fn write_primes_to_cache(cache_file: &PathBuf, cache: &PrimeCache) -> Result<(), std::io::Error> {
+    let mut buf = Vec::with_capacity(CACHE_MAGIC.len() + 1 + 8 + cache.primes.len());
+    buf.extend_from_slice(CACHE_MAGIC);
+    buf.push(CACHE_VERSION);
+    buf.extend_from_slice(&cache.upper_bound.to_le_bytes());
+
+    let mut prev = 0u64;
+    for &prime in &cache.primes {
+        write_varint(&mut buf, prime - prev);
+        prev = prime;
+    }
+
     let mut file = File::create(cache_file)?;
-    let data = serde_json::to_string(primes)?;
-    file.write_all(data.as_bytes())?;
+    file.write_all(&buf)?;
     Ok(())
 }
 
-fn compute_product(prime_powers: &HashMap<u64, u64>) -> BigUint {
+fn compute_product(prime_powers: &HashMap<BigUint, u64>) -> BigUint {
     prime_powers
         .iter()
-        .map(|(prime, power)| prime.to_biguint().unwrap().pow(*power as u32))
+        .map(|(prime, power)| prime.pow(*power as u32))
         .fold(BigUint::one(), |acc, x| acc * x)
 }
 
-fn log_guess(prime_powers: &HashMap<u64, u64>) {
+fn log_guess(prime_powers: &HashMap<BigUint, u64>) {
     let guess: Vec<String> = prime_powers
         .iter()
         .map(|(prime, power)| format!("{}^{}", prime, power))
@@ -86,6 +501,152 @@ fn log_guess(prime_powers: &HashMap<u64, u64>) {
     debug!("Current guess: {}", guess.join(" * "));
 }
 
+/// Fixed witness bases that make Miller-Rabin deterministic for any modulus
+/// below 3,317,044,064,679,887,385,961,981 (Pomerance/Selfridge/Wagstaff),
+/// which has 25 digits. `n` with 25 digits can still exceed that bound, so
+/// only moduli of at most [`MILLER_RABIN_DETERMINISTIC_DIGITS`] (24) digits
+/// are guaranteed to be under it; anything with more digits falls back to
+/// random witnesses instead.
+const MILLER_RABIN_WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+const MILLER_RABIN_DETERMINISTIC_DIGITS: usize = 24;
+
+/// Miller-Rabin primality test, deterministic for moduli with at most
+/// [`MILLER_RABIN_DETERMINISTIC_DIGITS`] decimal digits and probabilistic
+/// (20 random witnesses) beyond that.
+fn is_probably_prime(n: &BigUint) -> bool {
+    let two = BigUint::from(2u32);
+    if *n < two {
+        return false;
+    }
+    if *n == two {
+        return true;
+    }
+    if (n % &two).is_zero() {
+        return false;
+    }
+
+    let n_minus_one = n - BigUint::one();
+    let mut d = n_minus_one.clone();
+    let mut s = 0u32;
+    while (&d % &two).is_zero() {
+        d /= &two;
+        s += 1;
+    }
+
+    let witnesses: Vec<BigUint> = if n.to_string().len() <= MILLER_RABIN_DETERMINISTIC_DIGITS {
+        MILLER_RABIN_WITNESSES
+            .iter()
+            .map(|&w| BigUint::from(w))
+            .collect()
+    } else {
+        let mut rng = rand::thread_rng();
+        (0..20)
+            .map(|_| rng.gen_biguint_range(&two, &n_minus_one))
+            .collect()
+    };
+
+    'witness: for a in &witnesses {
+        if a < &two || a >= n {
+            continue;
+        }
+        let mut x = a.modpow(&d, n);
+        if x == BigUint::one() || x == n_minus_one {
+            continue;
+        }
+        for _ in 0..s.saturating_sub(1) {
+            x = x.modpow(&two, n);
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// Pollard's rho, Floyd-cycle variant: finds a single nontrivial divisor of
+/// the composite `n`. Retries with a fresh pseudo-random `c` whenever a
+/// cycle degenerates back to `n` itself.
+fn pollard_rho(n: &BigUint) -> BigUint {
+    let two = BigUint::from(2u32);
+    if (n % &two).is_zero() {
+        return two;
+    }
+
+    let mut rng = rand::thread_rng();
+    loop {
+        let c = rng.gen_biguint_range(&BigUint::one(), n);
+        let mut x = rng.gen_biguint_range(&two, n);
+        let mut y = x.clone();
+        let mut d = BigUint::one();
+
+        while d.is_one() {
+            x = (&x * &x + &c) % n;
+            y = (&y * &y + &c) % n;
+            y = (&y * &y + &c) % n;
+            let diff = if x > y { &x - &y } else { &y - &x };
+            d = diff.gcd(n);
+        }
+
+        if d != *n {
+            return d;
+        }
+        // This `c` produced a cycle collapsing back to n; try another.
+    }
+}
+
+/// Fully factors a cofactor with no small prime divisors, splitting
+/// composites with [`pollard_rho`] and testing primality with
+/// [`is_probably_prime`], merging every prime power found into `factors`.
+///
+/// `factors` is keyed on `BigUint` rather than `u64` because a prime found
+/// here can exceed `u64::MAX` once `n` is large enough to need this path.
+fn factorize_large(n: &BigUint, factors: &mut HashMap<BigUint, u64>) {
+    if n.is_one() {
+        return;
+    }
+    if is_probably_prime(n) {
+        *factors.entry(n.clone()).or_insert(0) += 1;
+        return;
+    }
+
+    let divisor = pollard_rho(n);
+    let cofactor = n / &divisor;
+    factorize_large(&divisor, factors);
+    factorize_large(&cofactor, factors);
+}
+
+/// Factor `n` using the supplied ascending prime list, dividing out each
+/// prime as far as it goes before moving to the next. Stops as soon as
+/// `p * p` exceeds the remaining cofactor or the prime list runs out; any
+/// cofactor left over is handed off to the Miller-Rabin/Pollard's rho
+/// subsystem in [`factorize_large`], since when the list runs out first it
+/// may be a product of large primes rather than a single prime.
+fn factorize(n: &BigUint, primes: &[u64]) -> HashMap<BigUint, u64> {
+    let mut factors: HashMap<BigUint, u64> = HashMap::new();
+    let mut remaining = n.clone();
+
+    for &prime in primes {
+        if remaining.is_one() {
+            break;
+        }
+        let p = prime.to_biguint().unwrap();
+        if &p * &p > remaining {
+            break;
+        }
+        while (&remaining % &p).is_zero() {
+            *factors.entry(p.clone()).or_insert(0) += 1;
+            remaining /= &p;
+        }
+    }
+
+    if remaining > BigUint::one() {
+        factorize_large(&remaining, &mut factors);
+    }
+
+    factors
+}
+
 fn main() {
     // Initialize logging
     init_logging();
@@ -104,99 +665,124 @@ fn main() {
     info!("Number to factorize: {}", number);
     info!("Number of digits: {}", number.to_string().len());
 
-    let sqrt_n = number.sqrt();
-    let sqrt_u64 = sqrt_n.to_u64_digits()[0];
-    let primes = generate_primes_up_to(sqrt_u64, args.cache.as_ref());
-    debug!("Generated primes up to sqrt(n): {:?}", primes);
+    // 0 and 1 have no prime factorization; handle them directly rather than
+    // running them through `factorize` and the reconstruction check below,
+    // which assumes `n > 1`.
+    if number.is_zero() || number.is_one() {
+        info!("{} has no prime factors", number);
+        println!(
+            "Prime factors found: {}",
+            to_string_pretty(&PrimeFactors {
+                factors: HashMap::new()
+            })
+            .unwrap()
+        );
+        return;
+    }
+
+    // Sieving all the way to sqrt(n) is infeasible once `n` has hundreds of
+    // digits, and `sqrt(n)` itself can exceed u64::MAX and silently
+    // truncate. Only strip primes up to a fixed small-prime bound here; any
+    // large remaining cofactor is handled by `factorize_large`'s
+    // Miller-Rabin/Pollard's rho subsystem instead of further sieving.
+    const SMALL_PRIME_BOUND: u64 = 10_000_000;
+    let cbrt_n = number.nth_root(3);
+    let sieve_bound = std::cmp::min(cbrt_n, BigUint::from(SMALL_PRIME_BOUND))
+        .to_u64_digits()
+        .first()
+        .copied()
+        .unwrap_or(0);
+    let prime_gen_start = Instant::now();
+    let (primes, gpu_timings) =
+        generate_primes(sieve_bound, args.cache.as_ref(), args.gpu, args.cpu_validate);
+    let prime_gen_time = prime_gen_start.elapsed();
+    debug!("Generated primes up to {}: {:?}", sieve_bound, primes);
 
     info!(
-        "Generated {} prime candidates up to sqrt({})",
+        "Generated {} small prime candidates up to {}",
         primes.len(),
-        number
+        sieve_bound
     );
 
-    let mut prime_powers: HashMap<u64, u64> = primes.iter().map(|&prime| (prime, 0)).collect();
-    let best_match = Arc::new(Mutex::new((BigUint::from(u64::MAX), prime_powers.clone())));
+    debug!("Factoring {} against the generated prime list", number);
+    let factorization_start = Instant::now();
+    let prime_powers = factorize(&number, &primes);
+    let factorization_time = factorization_start.elapsed();
+    log_guess(&prime_powers);
 
-    let total_iterations = 1000000;
-    let bar = ProgressBar::new(total_iterations as u64);
-    bar.set_style(
-        ProgressStyle::default_bar()
-            .template("{msg} {bar:40.cyan/blue} {pos}/{len}")
-            .expect("Failed to set progress bar style")
-            .progress_chars("#>-"),
-    );
-    bar.set_message("Processing guesses");
+    let reconstructed = compute_product(&prime_powers);
+    if reconstructed != number {
+        error!(
+            "Computed factors ({}) do not reconstruct the original number ({})",
+            reconstructed, number
+        );
+        eprintln!(
+            "Error: factorization is incomplete; computed factors reconstruct to {} instead of {}",
+            reconstructed, number
+        );
+        std::process::exit(1);
+    }
 
-    let found = Arc::new(Mutex::new(false));
+    info!("Found prime factors: {:?}", prime_powers);
+    let factors: HashMap<String, u64> = prime_powers
+        .into_iter()
+        .map(|(prime, power)| (prime.to_string(), power))
+        .collect();
+    println!(
+        "Prime factors found: {}",
+        to_string_pretty(&PrimeFactors { factors }).unwrap()
+    );
 
-    debug!("Starting parallel iteration with progress bar.");
-    (0..total_iterations)
-        .into_par_iter()
-        .progress_with(bar.clone())
-        .for_each(|iteration| {
-            let mut local_prime_powers = prime_powers.clone();
-            let product = compute_product(&local_prime_powers);
-
-            {
-                let mut found = found.lock().unwrap();
-                if *found {
-                    return;
-                }
-            }
+    if let Some(timings_path) = &args.timings {
+        append_timings_csv(
+            timings_path,
+            prime_gen_time,
+            factorization_time,
+            primes.len(),
+            number.to_string().len(),
+            gpu_timings.as_ref(),
+        )
+        .expect("Failed to write timings CSV");
+    }
+}
 
-            if product == number {
-                {
-                    let mut found = found.lock().unwrap();
-                    *found = true;
-                }
-                info!("Found prime factors: {:?}", local_prime_powers);
-            } else {
-                for prime in &primes {
-                    if let Some(power) = local_prime_powers.get_mut(prime) {
-                        *power += 1;
-                        break;
-                    }
-                }
-                log_guess(&local_prime_powers);
-
-                let mut best_match = best_match.lock().unwrap();
-                let current_distance = if &product > &number {
-                    &product - &number
-                } else {
-                    &number - &product
-                };
-                if current_distance < best_match.0 {
-                    best_match.0 = current_distance;
-                    best_match.1 = local_prime_powers.clone();
-                }
-            }
+/// Appends one row of per-phase timings to `path` as CSV, writing the header
+/// first if the file doesn't exist yet. GPU dispatch/host-compaction columns
+/// are left blank when the run didn't use the GPU backend.
+fn append_timings_csv(
+    path: &PathBuf,
+    prime_gen_time: Duration,
+    factorization_time: Duration,
+    prime_count: usize,
+    input_digits: usize,
+    gpu_timings: Option<&GpuTimings>,
+) -> Result<(), std::io::Error> {
+    let header_needed = !path.exists();
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
 
-            if iteration % 1000 == 0 {
-                warn!("Still running after {} iterations", iteration);
-            }
-        });
+    if header_needed {
+        writeln!(
+            file,
+            "prime_generation_secs,factorization_secs,prime_count,input_digits,gpu_dispatch_secs,gpu_host_compaction_secs"
+        )?;
+    }
 
-    bar.finish_with_message("Completed");
+    writeln!(
+        file,
+        "{:.6},{:.6},{},{},{},{}",
+        prime_gen_time.as_secs_f64(),
+        factorization_time.as_secs_f64(),
+        prime_count,
+        input_digits,
+        gpu_timings
+            .map(|t| format!("{:.6}", t.dispatch.as_secs_f64()))
+            .unwrap_or_default(),
+        gpu_timings
+            .map(|t| format!("{:.6}", t.host_compaction.as_secs_f64()))
+            .unwrap_or_default(),
+    )?;
 
-    let best_match = best_match.lock().unwrap();
-    if *found.lock().unwrap() {
-        println!(
-            "Prime factors found: {}",
-            to_string_pretty(&PrimeFactors {
-                factors: best_match.1.clone()
-            })
-            .unwrap()
-        );
-    } else {
-        println!(
-            "Failed to find prime factors. Best match: {}",
-            to_string_pretty(&PrimeFactors {
-                factors: best_match.1.clone()
-            })
-            .unwrap()
-        );
-    }
+    Ok(())
 }
 
 fn init_logging() {
@@ -204,3 +790,83 @@ fn init_logging() {
         .filter_level(LevelFilter::Debug)
         .init();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Smallest odd probable prime `>= start`, found with the in-crate
+    /// Miller-Rabin test so the fixtures below are self-verifying.
+    fn next_probable_prime(start: u64) -> BigUint {
+        let mut candidate = BigUint::from(start);
+        if (&candidate % 2u32).is_zero() {
+            candidate += 1u32;
+        }
+        while !is_probably_prime(&candidate) {
+            candidate += 2u32;
+        }
+        candidate
+    }
+
+    #[test]
+    fn factorize_round_trip_small_composite() {
+        let primes = sieve_base_primes(1000);
+        let n = BigUint::from(2u32 * 2 * 3 * 3 * 3 * 17 * 101);
+        let factors = factorize(&n, &primes);
+
+        assert_eq!(compute_product(&factors), n);
+        assert_eq!(factors.get(&BigUint::from(2u32)), Some(&2));
+        assert_eq!(factors.get(&BigUint::from(3u32)), Some(&3));
+        assert_eq!(factors.get(&BigUint::from(17u32)), Some(&1));
+        assert_eq!(factors.get(&BigUint::from(101u32)), Some(&1));
+    }
+
+    #[test]
+    fn factorize_round_trip_large_semiprime() {
+        // Two primes whose product exceeds u64::MAX, exercising the
+        // BigUint-keyed path in factorize_large/compute_product.
+        let p = next_probable_prime(10_000_000_000);
+        let q = next_probable_prime(20_000_000_000);
+        let n = &p * &q;
+        assert!(n > BigUint::from(u64::MAX));
+
+        let primes = sieve_base_primes(1000);
+        let factors = factorize(&n, &primes);
+
+        assert_eq!(compute_product(&factors), n);
+        assert_eq!(factors.get(&p), Some(&1));
+        assert_eq!(factors.get(&q), Some(&1));
+    }
+
+    #[test]
+    fn prime_cache_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "pfg_test_cache_{}_{}.bin",
+            std::process::id(),
+            line!()
+        ));
+        let primes = sieve_base_primes(1000);
+        let cache = PrimeCache {
+            upper_bound: 1000,
+            primes: primes.clone(),
+        };
+
+        write_primes_to_cache(&path, &cache).expect("failed to write prime cache");
+        let read_back = read_primes_from_cache(&path).expect("failed to read prime cache");
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(read_back.upper_bound, 1000);
+        assert_eq!(read_back.primes, primes);
+    }
+
+    #[test]
+    fn segmented_sieve_agrees_with_trial_division() {
+        let bound = 5_000u64;
+        let sieved = generate_primes_up_to(bound, None);
+        let trial: Vec<u64> = (2..=bound)
+            .filter(|&n| (2..=((n as f64).sqrt() as u64)).all(|d| n % d != 0))
+            .collect();
+
+        assert_eq!(sieved, trial);
+    }
+}